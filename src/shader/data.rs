@@ -0,0 +1,146 @@
+//! std140 uniform block buffers.
+//!
+//! Looking a `Uniform` up by name and setting it every frame doesn’t scale well once a shader
+//! needs many of them: each lookup is a string comparison and each set is a round-trip to the
+//! driver. `ShaderData<C, T>` instead packs `T` into a single GPU buffer, laid out according to the
+//! std140 rules GLSL uniform blocks use:
+//!
+//! - scalars are aligned to their own size ;
+//! - `vec2` is aligned to 8 bytes ;
+//! - `vec3`, `vec4` and matrix columns are aligned to 16 bytes ;
+//! - array elements and struct members are rounded up to a 16-byte boundary.
+//!
+//! Implement `Std140` by hand for the type you want to upload, computing each field’s offset with
+//! `align_offset` and the rules above; `ShaderData` will only ever write the bytes your
+//! implementation puts there, so getting the offsets wrong is the one way to desync the CPU and
+//! GPU views of the block.
+
+use std::marker::PhantomData;
+
+use shader::program::{HasProgram, ProgramError};
+
+/// Round `offset` up to the next multiple of `alignment`.
+///
+/// Useful when hand-writing a `Std140::std140_write` implementation: scalars align to their own
+/// size, `vec2` to 8 bytes, `vec3`/`vec4`/matrix columns to 16 bytes, and array elements or struct
+/// members to a 16-byte boundary.
+pub fn align_offset(offset: usize, alignment: usize) -> usize {
+  (offset + alignment - 1) / alignment * alignment
+}
+
+/// Implemented by types whose memory layout matches the std140 rules for a GLSL uniform block.
+pub trait Std140: Sized {
+  /// Size, in bytes, this value occupies once laid out per std140.
+  fn std140_size() -> usize;
+
+  /// Write this value into `buf`, following the std140 layout, starting at offset `0`.
+  fn std140_write(&self, buf: &mut [u8]);
+}
+
+/// Trait to implement to provide `ShaderData` features.
+pub trait HasShaderData {
+  type ShaderData;
+
+  /// Create a new GPU buffer able to hold `size` std140-laid-out bytes.
+  fn new_shader_data(size: usize) -> Result<Self::ShaderData, ShaderDataError>;
+  /// Free a shader data buffer.
+  fn free_shader_data(shader_data: &mut Self::ShaderData);
+  /// Replace the whole contents of a shader data buffer with `bytes`.
+  fn update_shader_data(shader_data: &Self::ShaderData, bytes: &[u8]);
+  /// Bind a shader data buffer to a uniform block binding point.
+  fn bind_shader_data(shader_data: &Self::ShaderData, binding_point: u32);
+}
+
+/// A GPU buffer whose contents are `T`, laid out per std140, ready to be bound to a uniform block.
+#[derive(Debug)]
+pub struct ShaderData<C, T> where C: HasShaderData {
+  pub repr: C::ShaderData,
+  size: usize,
+  _t: PhantomData<T>
+}
+
+impl<C, T> Drop for ShaderData<C, T> where C: HasShaderData {
+  fn drop(&mut self) {
+    C::free_shader_data(&mut self.repr)
+  }
+}
+
+impl<C, T> ShaderData<C, T> where C: HasShaderData, T: Std140 {
+  /// Create a new `ShaderData`, uploading `value`’s std140 representation to a fresh GPU buffer.
+  pub fn new(value: &T) -> Result<Self, ShaderDataError> {
+    let size = T::std140_size();
+    let repr = C::new_shader_data(size)?;
+    let mut bytes = vec![0u8; size];
+    value.std140_write(&mut bytes);
+    C::update_shader_data(&repr, &bytes);
+
+    Ok(ShaderData {
+      repr: repr,
+      size: size,
+      _t: PhantomData
+    })
+  }
+
+  /// Replace this buffer’s contents with `value`’s std140 representation.
+  pub fn set(&self, value: &T) {
+    let mut bytes = vec![0u8; self.size];
+    value.std140_write(&mut bytes);
+    C::update_shader_data(&self.repr, &bytes)
+  }
+}
+
+/// Handle to a program’s uniform block, obtained via `UniformBuilder::ask_block`.
+///
+/// `bind` a `ShaderData` to it to feed the block’s contents to the program. Its binding point is
+/// released back to the context’s allocator when this value is dropped.
+#[derive(Debug)]
+pub struct UniformBlock<C> where C: HasProgram {
+  binding: u32,
+  _c: PhantomData<C>
+}
+
+impl<C> Drop for UniformBlock<C> where C: HasProgram {
+  fn drop(&mut self) {
+    C::release_uniform_block_binding(self.binding)
+  }
+}
+
+impl<C> UniformBlock<C> where C: HasProgram {
+  pub(crate) fn new(binding: u32) -> Self {
+    UniformBlock {
+      binding: binding,
+      _c: PhantomData
+    }
+  }
+
+  /// Bind a `ShaderData` to this uniform block’s binding point.
+  pub fn bind<T>(&self, shader_data: &ShaderData<C, T>) where C: HasShaderData {
+    C::bind_shader_data(&shader_data.repr, self.binding)
+  }
+}
+
+#[derive(Debug)]
+pub enum ShaderDataError {
+  CreationFailed(String)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn align_offset_rounds_up_to_the_next_multiple() {
+    assert_eq!(align_offset(0, 16), 0);
+    assert_eq!(align_offset(4, 16), 16);
+    assert_eq!(align_offset(16, 16), 16);
+    assert_eq!(align_offset(17, 16), 32);
+  }
+
+  #[test]
+  fn align_offset_matches_std140_vec3_after_vec2() {
+    // a vec2 at offset 0 occupies 8 bytes; a following vec3 must start on a 16-byte boundary
+    assert_eq!(align_offset(8, 16), 16);
+    // a vec4 following that vec3 (offset 16, size 12) must also land on a 16-byte boundary
+    assert_eq!(align_offset(16 + 12, 16), 32);
+  }
+}
@@ -23,12 +23,16 @@
 //! In order to customize the behavior of your shader programs, you have access to *uniforms*. For
 //! more details about them, see the documentation for the type `Uniform` and `Uniformable`. When
 //! creating a new shader program, you have to provide code to declare its *uniform interface*. The
-//! *uniform interface* refers to a type of your own that will be kept by the shader program and
-//! exposed to you when you’ll express the need to update its uniforms. That *uniform interface* is
-//! created via a closure you pass. That closure takes as arguments a function used to retrieve
-//! `Uniform`s from the program being constructed. That pattern, that can be a bit overwhelming at
-//! first, is important to keep things safe and functional. Keep in mind that you can make the
-//! closure fail, so that you can notify a `Uniform` lookup failure, for instance.
+//! *uniform interface* refers to a type of your own, `Uni`, that will be kept by the shader program
+//! and handed back to you, already typed, whenever you need to update its uniforms. That *uniform
+//! interface* is built via a closure you pass to `new`. That closure takes as argument a
+//! `UniformBuilder` you can `ask` `Uniform`s from, and returns your `Uni` type, wrapped in a
+//! `Result` so that you can fail the whole build on a lookup you consider fatal.
+//!
+//! Not every lookup failure is fatal, though: a uniform that got optimized out by the GLSL compiler,
+//! or whose GLSL type doesn’t match what you asked for, doesn’t prevent the program from linking.
+//! Those situations are reported as non-fatal `ProgramWarning`s instead, collected alongside the
+//! built `Program` in a `BuiltProgram`. Use `ignore_warnings` if you don’t care about them.
 //!
 //! You can create a `Program` with its `new` associated function.
 //!
@@ -36,73 +40,635 @@
 //!
 //! ```
 //! // assume we have a vertex shader `vs` and fragment shader `fs`
-//! let program = Program::new(None, &vs, None, &fs, |get_uni| {
-//!   let resolution: Result<Uniform<[f32; 2]>, _> = get_uni("resolution");
-//!   let time: Result<Uniform<f32>, _> = get_uni("time");
+//! struct ShaderInterface {
+//!   resolution: Uniform<[f32; 2]>,
+//!   time: Uniform<f32>
+//! }
 //!
-//!   if let Err(err) = resolution {
-//!     return Err(err);
-//!   }
-//!   let resolution = resolution.unwrap();
+//! let BuiltProgram { program, warnings } = Program::new(None, &vs, None, &fs, None, |builder| {
+//!   Ok(ShaderInterface {
+//!     resolution: builder.ask("resolution")?,
+//!     time: builder.ask("time")?
+//!   })
+//! }).expect("program creation");
 //!
-//!   if let Err(err) = time {
-//!     return Err(err);
-//!   }
-//!   let time = time.unwrap();
+//! for warning in &warnings {
+//!   eprintln!("{:?}", warning);
+//! }
 //!
-//!   Ok(resolution, time)
+//! program.update(|iface| {
+//!   // set iface.resolution and iface.time here
 //! });
 //! ```
+//!
+//! For a live-coding workflow, `Program::from_files` loads the same stages straight from GLSL
+//! files on disk, and `WatchedProgram` goes one step further by watching them and rebuilding the
+//! program whenever one changes, without tearing the application down on a `ProgramError`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
 
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use shader::data::{HasShaderData, UniformBlock};
 use shader::stage::*;
-use shader::uniform::{HasUniform, Uniform, Uniformable, UniformName};
+use shader::uniform::{HasUniform, Uniform, Uniformable, UniformName, UniformType};
 
 /// Trait to implement to provide shader program features.
-pub trait HasProgram: HasStage + HasUniform {
+pub trait HasProgram: HasStage + HasUniform + HasShaderData {
   type Program;
+  /// GPU buffer transform-feedback output gets captured into.
+  type CaptureBuffer;
 
   /// Create a new program by linking it with stages.
-  fn new_program(tess: Option<(&Self::AStage, &Self::AStage)>, vertex: &Self::AStage, geometry: Option<&Self::AStage>, fragment: &Self::AStage) -> Result<Self::Program, ProgramError>;
+  ///
+  /// When `varyings` is provided, the named vertex (or geometry) output varyings are registered
+  /// for transform feedback via `glTransformFeedbackVaryings`, in the given `FeedbackVaryingsMode`,
+  /// before the program is linked.
+  fn new_program(tess: Option<(&Self::AStage, &Self::AStage)>, vertex: &Self::AStage, geometry: Option<&Self::AStage>, fragment: &Self::AStage, varyings: Option<(&[&str], FeedbackVaryingsMode)>) -> Result<Self::Program, ProgramError>;
   /// Free a program.
   fn free_program(program: &mut Self::Program);
-  ///
-  fn map_uniform(program: &Self::Program, name: UniformName) -> Result<Self::U, ProgramError>;
+  /// Map a uniform by name. Besides the uniform handle itself, implementations should report a
+  /// `ProgramWarning` – instead of failing outright – when the uniform is inactive or its GLSL
+  /// type doesn’t match the one asked for.
+  fn map_uniform(program: &Self::Program, name: UniformName) -> Result<(Self::U, Option<ProgramWarning>), ProgramError>;
   ///
   fn update_uniforms<F>(program: &Self::Program, f: F) where F: Fn();
+  /// Retrieve the implementation-defined binary representation of a linked program, as given by
+  /// `glGetProgramBinary`.
+  fn program_binary(program: &Self::Program) -> Result<(u32, Vec<u8>), ProgramError>;
+  /// Re-create a program straight from a binary blob previously obtained via `program_binary`, as
+  /// given by `glProgramBinary`.
+  fn new_program_from_binary(format: u32, data: &[u8]) -> Result<Self::Program, ProgramError>;
+  /// Create a new compute program by linking it with a single compute stage.
+  fn new_compute_program(compute: &Self::AStage) -> Result<Self::Program, ProgramError>;
+  /// Reserve and return the next free uniform-block binding point, as seen from `program`’s
+  /// context.
+  ///
+  /// Binding points are a single namespace shared by every program living in the same context, not
+  /// a per-program one, so implementations must hand out values that never collide with ones
+  /// already claimed by another live `Program` or `ComputeProgram` in that context. `program` is
+  /// taken only to identify which context’s binding points to hand out from, the same way
+  /// `map_uniform_block` and `bind_uniform_block` are scoped to a program without being specific to
+  /// its own bindings; it fails if every binding point supported by the driver
+  /// (`GL_MAX_UNIFORM_BUFFER_BINDINGS` or equivalent) is currently claimed.
+  ///
+  /// Every binding point returned here is eventually handed back via
+  /// `release_uniform_block_binding`, called when the `UniformBlock` wrapping it is dropped, so a
+  /// long-running process that keeps rebuilding programs (e.g. `WatchedProgram` on every file save)
+  /// doesn’t exhaust the binding space over time.
+  fn next_uniform_block_binding(program: &Self::Program) -> Result<u32, ProgramError>;
+  /// Return a uniform-block binding point previously reserved via `next_uniform_block_binding` to
+  /// the free list, making it available for reuse.
+  fn release_uniform_block_binding(binding: u32);
+  /// Dispatch a compute program over a 3D work-group count, then issue a memory barrier so buffer
+  /// and image writes it performed become visible to subsequent raster passes.
+  fn dispatch_compute(program: &Self::Program, work_groups: [u32; 3]);
+  /// Map a uniform block by name and return its index.
+  fn map_uniform_block(program: &Self::Program, name: &str) -> Result<u32, ProgramError>;
+  /// Bind a uniform block index to a binding point.
+  fn bind_uniform_block(program: &Self::Program, block_index: u32, binding_point: u32);
+  /// Enumerate every active uniform in the program: its name, GLSL type and array size.
+  fn uniforms(program: &Self::Program) -> Vec<(String, UniformType, usize)>;
+  /// Enumerate every active uniform block in the program: its name and byte size.
+  fn uniform_blocks(program: &Self::Program) -> Vec<(String, usize)>;
+  /// Look a fragment shader output’s binding location up by name.
+  fn frag_data_location(program: &Self::Program, name: &str) -> Option<u32>;
+  /// Create a new GPU buffer able to capture `size` bytes of transform-feedback output.
+  fn new_capture_buffer(size: usize) -> Result<Self::CaptureBuffer, ProgramError>;
+  /// Free a capture buffer.
+  fn free_capture_buffer(buffer: &mut Self::CaptureBuffer);
+  /// Bind `buffer` as the transform-feedback target and start capturing, as given by
+  /// `glBeginTransformFeedback`.
+  fn begin_transform_feedback(program: &Self::Program, buffer: &Self::CaptureBuffer);
+  /// Stop capturing, as given by `glEndTransformFeedback`.
+  fn end_transform_feedback();
 }
 
 #[derive(Debug)]
-pub struct Program<C> where C: HasProgram {
+pub struct Program<C, Uni> where C: HasProgram {
   pub repr: C::Program,
+  uniforms: Uni
 }
 
-impl<C> Drop for Program<C> where C: HasProgram {
+impl<C, Uni> Drop for Program<C, Uni> where C: HasProgram {
   fn drop(&mut self) {
     C::free_program(&mut self.repr)
   }
 }
 
-impl<C> Program<C> where C: HasProgram {
-  pub fn new(tess: Option<(&Stage<C, TessellationControlShader>, &Stage<C, TessellationEvaluationShader>)>, vertex: &Stage<C, VertexShader>, geometry: Option<&Stage<C, GeometryShader>>, fragment: &Stage<C, FragmentShader>) -> Result<Self, ProgramError> {
-    C::new_program(tess.map(|(tcs, tes)| (&tcs.repr, &tes.repr)), &vertex.repr, geometry.map(|g| &g.repr), &fragment.repr).map(|repr| {
-      Program {
+impl<C, Uni> Program<C, Uni> where C: HasProgram {
+  /// Create a new `Program` by linking it with stages and build its uniform interface.
+  ///
+  /// `get_uni` is handed a `UniformBuilder` used to `ask` the `Uniform`s making up `Uni`. Failing
+  /// that closure fails the whole build; a `Uniform` that is merely inactive or mismatched is
+  /// instead reported as a `ProgramWarning` on the returned `BuiltProgram`.
+  pub fn new<GetUni>(
+    tess: Option<(&Stage<C, TessellationControlShader>, &Stage<C, TessellationEvaluationShader>)>,
+    vertex: &Stage<C, VertexShader>,
+    geometry: Option<&Stage<C, GeometryShader>>,
+    fragment: &Stage<C, FragmentShader>,
+    varyings: Option<(&[&str], FeedbackVaryingsMode)>,
+    get_uni: GetUni
+  ) -> Result<BuiltProgram<C, Uni>, ProgramError>
+  where GetUni: FnOnce(&mut UniformBuilder<C>) -> Result<Uni, ProgramError> {
+    let repr = C::new_program(tess.map(|(tcs, tes)| (&tcs.repr, &tes.repr)), &vertex.repr, geometry.map(|g| &g.repr), &fragment.repr, varyings)?;
+    let (uniforms, warnings) = {
+      let mut builder = UniformBuilder::new(&repr);
+      let uniforms = get_uni(&mut builder)?;
+      (uniforms, builder.warnings)
+    };
+
+    Ok(BuiltProgram {
+      program: Program {
         repr: repr,
+        uniforms: uniforms
+      },
+      warnings: warnings
+    })
+  }
+
+  pub fn update<F>(&self, f: F) where F: Fn(&Uni) {
+    let uniforms = &self.uniforms;
+    C::update_uniforms(&self.repr, || f(uniforms))
+  }
+
+  /// Enumerate every active uniform in this program: its name, GLSL type and array size (`1` for
+  /// a scalar uniform).
+  pub fn uniforms(&self) -> Vec<(String, UniformType, usize)> {
+    C::uniforms(&self.repr)
+  }
+
+  /// Enumerate every active uniform block in this program: its name and byte size.
+  pub fn uniform_blocks(&self) -> Vec<(String, usize)> {
+    C::uniform_blocks(&self.repr)
+  }
+
+  /// Look a fragment shader output’s binding location up by name.
+  ///
+  /// Returns `None` if `name` doesn’t name an active fragment output.
+  pub fn frag_data_location(&self, name: &str) -> Option<u32> {
+    C::frag_data_location(&self.repr, name)
+  }
+
+  /// Retrieve an opaque, implementation-defined binary representation of this linked program.
+  ///
+  /// The resulting `ProgramBinary` can be persisted (to disk, for instance) and handed back to
+  /// `from_binary` on a later run to skip GLSL compilation and linking altogether. Because the
+  /// format is driver- and hardware-defined, callers should always be ready to fall back to `new`
+  /// if `from_binary` fails.
+  pub fn to_binary(&self) -> Result<ProgramBinary, ProgramError> {
+    C::program_binary(&self.repr).map(|(format, data)| ProgramBinary { format, data })
+  }
+
+  /// Rebuild a `Program` from a `ProgramBinary` obtained through `to_binary`, re-running `get_uni`
+  /// to re-map its uniform interface.
+  ///
+  /// This can fail if the binary was produced by a different driver, GPU or format than the one
+  /// currently in use, in which case `ProgramError::BinaryRejected` is returned and the caller
+  /// should fall back to linking from sources with `new`.
+  pub fn from_binary<GetUni>(binary: &ProgramBinary, get_uni: GetUni) -> Result<BuiltProgram<C, Uni>, ProgramError>
+  where GetUni: FnOnce(&mut UniformBuilder<C>) -> Result<Uni, ProgramError> {
+    let repr = C::new_program_from_binary(binary.format, &binary.data)?;
+    let (uniforms, warnings) = {
+      let mut builder = UniformBuilder::new(&repr);
+      let uniforms = get_uni(&mut builder)?;
+      (uniforms, builder.warnings)
+    };
+
+    Ok(BuiltProgram {
+      program: Program {
+        repr: repr,
+        uniforms: uniforms
+      },
+      warnings: warnings
+    })
+  }
+
+  /// Build a `Program` by reading its stages’ GLSL sources straight from files on disk.
+  ///
+  /// Otherwise behaves exactly like `new`: the same `varyings` registration and the same
+  /// `get_uni` uniform-interface closure apply.
+  pub fn from_files<GetUni>(
+    paths: &ProgramPaths,
+    varyings: Option<(&[&str], FeedbackVaryingsMode)>,
+    get_uni: GetUni
+  ) -> Result<BuiltProgram<C, Uni>, ProgramError>
+  where GetUni: FnOnce(&mut UniformBuilder<C>) -> Result<Uni, ProgramError> {
+    let tess = match paths.tess {
+      Some((ref tcs_path, ref tes_path)) => Some((read_stage(tcs_path)?, read_stage(tes_path)?)),
+      None => None
+    };
+    let vertex = read_stage(&paths.vertex)?;
+    let geometry = match paths.geometry {
+      Some(ref path) => Some(read_stage(path)?),
+      None => None
+    };
+    let fragment = read_stage(&paths.fragment)?;
+
+    Self::new(tess.as_ref().map(|(tcs, tes)| (tcs, tes)), &vertex, geometry.as_ref(), &fragment, varyings, get_uni)
+  }
+}
+
+/// Read a shader stage’s GLSL source from `path` and compile it.
+fn read_stage<C, K>(path: &Path) -> Result<Stage<C, K>, ProgramError> where C: HasStage {
+  let source = fs::read_to_string(path).map_err(|err| ProgramError::SourceError(format!("{}: {}", path.display(), err)))?;
+  Stage::new(&source).map_err(|err| ProgramError::SourceError(format!("{}: {:?}", path.display(), err)))
+}
+
+/// Paths to the GLSL sources making up a program, as used by `Program::from_files` and
+/// `WatchedProgram`.
+#[derive(Clone, Debug)]
+pub struct ProgramPaths {
+  pub tess: Option<(PathBuf, PathBuf)>,
+  pub vertex: PathBuf,
+  pub geometry: Option<PathBuf>,
+  pub fragment: PathBuf
+}
+
+impl ProgramPaths {
+  /// Every source path making up this program, for watch registration.
+  fn all(&self) -> Vec<&Path> {
+    let mut paths = vec![self.vertex.as_path(), self.fragment.as_path()];
+
+    if let Some((ref tcs, ref tes)) = self.tess {
+      paths.push(tcs.as_path());
+      paths.push(tes.as_path());
+    }
+
+    if let Some(ref geometry) = self.geometry {
+      paths.push(geometry.as_path());
+    }
+
+    paths
+  }
+}
+
+/// A `Program` that watches its source files on disk and transparently rebuilds itself whenever
+/// one of them changes.
+///
+/// Call `poll` on whatever cadence fits your main loop: it drains pending filesystem events and,
+/// if any watched source changed, reloads the sources, relinks the program and re-runs the
+/// uniform-interface closure. A rebuild that fails to compile or link leaves the last good
+/// `Program` alive and returns its `ProgramError` instead of panicking, so a typo in a live-edited
+/// shader doesn’t tear the application down.
+pub struct WatchedProgram<C, Uni, GetUni> where C: HasProgram {
+  built: BuiltProgram<C, Uni>,
+  paths: ProgramPaths,
+  varyings: Option<(Vec<String>, FeedbackVaryingsMode)>,
+  get_uni: GetUni,
+  _watcher: RecommendedWatcher,
+  events: Receiver<DebouncedEvent>
+}
+
+impl<C, Uni, GetUni> WatchedProgram<C, Uni, GetUni>
+where C: HasProgram, GetUni: Fn(&mut UniformBuilder<C>) -> Result<Uni, ProgramError> {
+  /// Build the program from `paths` and start watching its sources for changes.
+  pub fn new(paths: ProgramPaths, varyings: Option<(&[&str], FeedbackVaryingsMode)>, get_uni: GetUni) -> Result<Self, ProgramError> {
+    let owned_varyings = varyings.map(|(names, mode)| (names.iter().map(|n| n.to_string()).collect(), mode));
+    let built = Program::from_files(&paths, varyings, |builder| get_uni(builder))?;
+
+    let (tx, events) = channel();
+    let mut watcher = watcher(tx, Duration::from_millis(200)).map_err(|err| ProgramError::SourceError(format!("{:?}", err)))?;
+
+    for path in paths.all() {
+      watcher.watch(path, RecursiveMode::NonRecursive).map_err(|err| ProgramError::SourceError(format!("{}: {:?}", path.display(), err)))?;
+    }
+
+    Ok(WatchedProgram {
+      built: built,
+      paths: paths,
+      varyings: owned_varyings,
+      get_uni: get_uni,
+      _watcher: watcher,
+      events: events
+    })
+  }
+
+  /// Drain pending filesystem events and rebuild the program if any watched source changed.
+  ///
+  /// Returns the rebuilt program’s warnings on a successful rebuild, `None` if nothing changed,
+  /// or the `ProgramError` of a failed rebuild, in which case the previously built `Program` is
+  /// left untouched.
+  pub fn poll(&mut self) -> Result<Option<Vec<ProgramWarning>>, ProgramError> {
+    // drain the whole channel rather than short-circuiting on the first reload-worthy event, so a
+    // burst of save events collapses into a single rebuild instead of leaking one to the next poll
+    let changed = self.events.try_iter().filter(is_reload_trigger).count() > 0;
+
+    if !changed {
+      return Ok(None);
+    }
+
+    let owned_varyings = self.varyings.as_ref().map(|(names, mode)| (names.iter().map(String::as_str).collect::<Vec<_>>(), *mode));
+    let varyings = owned_varyings.as_ref().map(|(names, mode)| (names.as_slice(), *mode));
+    let get_uni = &self.get_uni;
+
+    match Program::from_files(&self.paths, varyings, |builder| get_uni(builder)) {
+      Ok(built) => {
+        let warnings = built.warnings.clone();
+        self.built = built;
+        Ok(Some(warnings))
       }
+
+      Err(err) => Err(err)
+    }
+  }
+
+  /// The program as it currently stands: the last successfully built one.
+  pub fn program(&self) -> &Program<C, Uni> {
+    &self.built.program
+  }
+}
+
+/// Whether a filesystem event should trigger a rebuild, ignoring the purely informational
+/// `NoticeWrite` / `NoticeRemove` / `Rescan` events `notify` emits ahead of the real one.
+fn is_reload_trigger(event: &DebouncedEvent) -> bool {
+  match *event {
+    DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) | DebouncedEvent::Rescan => false,
+    _ => true
+  }
+}
+
+/// A compute program, built from a single `Stage<C, ComputeShader>`.
+///
+/// Compute programs cannot be mixed with the raster stages (vertex, tessellation, geometry,
+/// fragment): they run on their own and are dispatched over a 3D work-group count through a
+/// `ComputeGate` instead of being fed to a `TessGate`. Just like `Program`, a `ComputeProgram`
+/// carries a typed uniform interface `Uni`, built the same way via a `UniformBuilder` closure —
+/// including uniform blocks, so a compute shader can be fed `ShaderData` buffers (particle counts,
+/// simulation parameters, and the like).
+#[derive(Debug)]
+pub struct ComputeProgram<C, Uni> where C: HasProgram {
+  pub repr: C::Program,
+  uniforms: Uni
+}
+
+impl<C, Uni> Drop for ComputeProgram<C, Uni> where C: HasProgram {
+  fn drop(&mut self) {
+    C::free_program(&mut self.repr)
+  }
+}
+
+impl<C, Uni> ComputeProgram<C, Uni> where C: HasProgram {
+  /// Create a new `ComputeProgram` by linking it with a single compute stage and build its
+  /// uniform interface.
+  ///
+  /// Behaves exactly like `Program::new`: `get_uni` is handed a `UniformBuilder` to `ask` the
+  /// `Uniform`s and uniform blocks making up `Uni`, and the non-fatal warnings it collects ride
+  /// along on the returned `BuiltComputeProgram`.
+  pub fn new<GetUni>(compute: &Stage<C, ComputeShader>, get_uni: GetUni) -> Result<BuiltComputeProgram<C, Uni>, ProgramError>
+  where GetUni: FnOnce(&mut UniformBuilder<C>) -> Result<Uni, ProgramError> {
+    let repr = C::new_compute_program(&compute.repr)?;
+    let (uniforms, warnings) = {
+      let mut builder = UniformBuilder::new(&repr);
+      let uniforms = get_uni(&mut builder)?;
+      (uniforms, builder.warnings)
+    };
+
+    Ok(BuiltComputeProgram {
+      program: ComputeProgram {
+        repr: repr,
+        uniforms: uniforms
+      },
+      warnings: warnings
     })
   }
 
-  pub fn uniform<T>(&self, name: &str) -> Result<Uniform<C, T>, ProgramError> where T: Uniformable {
-    C::map_uniform(&self.repr, UniformName::StringName(String::from(name))).map(|u| Uniform::new(u))
+  pub fn update<F>(&self, f: F) where F: Fn(&Uni) {
+    let uniforms = &self.uniforms;
+    C::update_uniforms(&self.repr, || f(uniforms))
   }
+}
+
+/// A `ComputeProgram` fresh out of linking, along with the non-fatal `ProgramWarning`s collected
+/// while building its uniform interface.
+#[derive(Debug)]
+pub struct BuiltComputeProgram<C, Uni> where C: HasProgram {
+  pub program: ComputeProgram<C, Uni>,
+  pub warnings: Vec<ProgramWarning>
+}
 
-  pub fn update<F>(&self, f: F) where F: Fn() {
-    C::update_uniforms(&self.repr, f)
+impl<C, Uni> BuiltComputeProgram<C, Uni> where C: HasProgram {
+  /// Discard the `warnings` and keep only the `ComputeProgram`, for callers that don’t care about
+  /// inactive or mismatched uniforms.
+  pub fn ignore_warnings(self) -> ComputeProgram<C, Uni> {
+    self.program
   }
 }
 
+/// Gate used to dispatch `ComputeProgram`s against a context, mirroring how `ShadingGate` /
+/// `RenderGate` / `TessGate` scope raster work instead of being called as a bare method.
 #[derive(Debug)]
-pub enum ProgramError {
-  LinkFailed(String),
+pub struct ComputeGate<'a, C> where C: 'a + HasProgram {
+  _context: &'a C
+}
+
+impl<'a, C> ComputeGate<'a, C> where C: 'a + HasProgram {
+  pub fn new(context: &'a C) -> Self {
+    ComputeGate { _context: context }
+  }
+
+  /// Dispatch `program` over a 3D work-group count.
+  ///
+  /// Results written to buffers or images are guaranteed visible to any raster pass recorded after
+  /// this call returns.
+  pub fn dispatch<Uni>(&self, program: &ComputeProgram<C, Uni>, work_groups: [u32; 3]) {
+    C::dispatch_compute(&program.repr, work_groups)
+  }
+}
+
+/// Transform-feedback varyings layout mode, mirroring `glTransformFeedbackVaryings`’s `bufferMode`
+/// argument.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeedbackVaryingsMode {
+  /// All captured varyings are interleaved into a single buffer.
+  Interleaved,
+  /// Each captured varying is written to its own buffer.
+  Separate
+}
+
+/// GPU buffer transform-feedback output is captured into, targeted by `FeedbackGate::capture`.
+#[derive(Debug)]
+pub struct CaptureBuffer<C> where C: HasProgram {
+  repr: C::CaptureBuffer
+}
+
+impl<C> Drop for CaptureBuffer<C> where C: HasProgram {
+  fn drop(&mut self) {
+    C::free_capture_buffer(&mut self.repr)
+  }
+}
+
+impl<C> CaptureBuffer<C> where C: HasProgram {
+  /// Create a new `CaptureBuffer` able to hold `size` bytes of captured output.
+  pub fn new(size: usize) -> Result<Self, ProgramError> {
+    C::new_capture_buffer(size).map(|repr| CaptureBuffer { repr })
+  }
+}
+
+/// Gate used to scope transform-feedback capture around a render pass, mirroring how `RenderGate`
+/// / `TessGate` scope a draw call instead of capture being a bare method on `Program`.
+#[derive(Debug)]
+pub struct FeedbackGate<'a, C> where C: 'a + HasProgram {
+  _context: &'a C
+}
+
+impl<'a, C> FeedbackGate<'a, C> where C: 'a + HasProgram {
+  pub fn new(context: &'a C) -> Self {
+    FeedbackGate { _context: context }
+  }
+
+  /// Begin transform-feedback capture into `buffer`, run `f` — typically a `tess_gate.render` call
+  /// over the stages whose output varyings were registered in `Program::new` — then end the
+  /// capture.
+  pub fn capture<Uni, F>(&self, program: &Program<C, Uni>, buffer: &CaptureBuffer<C>, f: F) where F: FnOnce() {
+    C::begin_transform_feedback(&program.repr, &buffer.repr);
+    f();
+    C::end_transform_feedback();
+  }
+}
+
+/// A `Program` fresh out of linking, along with the non-fatal `ProgramWarning`s collected while
+/// building its uniform interface.
+#[derive(Debug)]
+pub struct BuiltProgram<C, Uni> where C: HasProgram {
+  pub program: Program<C, Uni>,
+  pub warnings: Vec<ProgramWarning>
+}
+
+impl<C, Uni> BuiltProgram<C, Uni> where C: HasProgram {
+  /// Discard the `warnings` and keep only the `Program`, for callers that don’t care about
+  /// inactive or mismatched uniforms.
+  pub fn ignore_warnings(self) -> Program<C, Uni> {
+    self.program
+  }
+}
+
+/// Used to build a `Program`’s or `ComputeProgram`’s uniform interface by asking `Uniform`s out of
+/// it by name.
+///
+/// A `UniformBuilder` is only ever handed to the closure passed to `Program::new` / `from_binary`
+/// / `from_files` or `ComputeProgram::new`, and collects the `ProgramWarning`s yielded by inactive
+/// or mismatched uniforms along the way.
+#[derive(Debug)]
+pub struct UniformBuilder<'a, C> where C: 'a + HasProgram {
+  program: &'a C::Program,
+  warnings: Vec<ProgramWarning>
+}
+
+impl<'a, C> UniformBuilder<'a, C> where C: 'a + HasProgram {
+  fn new(program: &'a C::Program) -> Self {
+    UniformBuilder {
+      program: program,
+      warnings: Vec::new()
+    }
+  }
+
+  /// Ask a `Uniform` by name.
+  ///
+  /// If the uniform is inactive in the program or its GLSL type doesn’t match `T`, a handle is
+  /// still returned and a `ProgramWarning` is recorded instead of failing the build.
+  pub fn ask<T>(&mut self, name: &str) -> Result<Uniform<C, T>, ProgramError> where T: Uniformable {
+    let (u, warning) = C::map_uniform(self.program, UniformName::StringName(String::from(name)))?;
+
+    if let Some(warning) = warning {
+      self.warnings.push(warning);
+    }
+
+    Ok(Uniform::new(u))
+  }
+
+  /// Ask a uniform block by name, to later `bind` a `ShaderData` to it.
+  ///
+  /// The returned `UniformBlock`’s binding point is reserved from the context-wide allocator
+  /// (`HasProgram::next_uniform_block_binding`), so it never collides with one already claimed by
+  /// another program built against the same context, and is released back to that allocator when
+  /// the `UniformBlock` is dropped.
+  pub fn ask_block(&mut self, name: &str) -> Result<UniformBlock<C>, ProgramError> {
+    let index = C::map_uniform_block(self.program, name)?;
+    let binding = C::next_uniform_block_binding(self.program)?;
+
+    C::bind_uniform_block(self.program, index, binding);
+
+    Ok(UniformBlock::new(binding))
+  }
+}
+
+/// Opaque, implementation-defined binary representation of a linked `Program`.
+///
+/// A `ProgramBinary` carries both the driver-reported format enum and the raw byte blob returned
+/// by `glGetProgramBinary`; both are required to hand the blob back to `glProgramBinary` later on.
+#[derive(Clone, Debug)]
+pub struct ProgramBinary {
+  format: u32,
+  data: Vec<u8>
+}
+
+impl ProgramBinary {
+  /// Implementation-defined format of the binary data, as reported by the driver.
+  pub fn format(&self) -> u32 {
+    self.format
+  }
+
+  /// Raw binary blob, as returned by `glGetProgramBinary`.
+  pub fn data(&self) -> &[u8] {
+    &self.data
+  }
+}
+
+/// Non-fatal issue encountered while building a `Program`’s uniform interface.
+///
+/// Unlike a `ProgramError`, a `ProgramWarning` doesn’t prevent the program from linking nor the
+/// uniform interface from being built; the affected `Uniform` handle is simply inert.
+#[derive(Clone, Debug)]
+pub enum ProgramWarning {
+  /// The uniform got optimized out by the GLSL compiler and has no effect when set.
   InactiveUniform(String),
+  /// The uniform exists but its GLSL type doesn’t match the type it was asked with.
   UniformTypeMismatch(String)
 }
+
+#[derive(Debug)]
+pub enum ProgramError {
+  LinkFailed(String),
+  /// The driver rejected a binary handed to `Program::from_binary`, typically because it was
+  /// produced by a different driver version, GPU or format than the one currently in use.
+  BinaryRejected(String),
+  /// A shader source file couldn’t be read from disk or failed to compile, as encountered by
+  /// `Program::from_files` or `WatchedProgram`; `WatchedProgram::new` also reports a filesystem
+  /// watcher that couldn’t be created or couldn’t watch a source path this way.
+  SourceError(String)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::path::PathBuf;
+  use std::sync::mpsc::channel;
+
+  #[test]
+  fn is_reload_trigger_ignores_informational_events() {
+    assert!(!is_reload_trigger(&DebouncedEvent::NoticeWrite(PathBuf::from("a.glsl"))));
+    assert!(!is_reload_trigger(&DebouncedEvent::NoticeRemove(PathBuf::from("a.glsl"))));
+    assert!(!is_reload_trigger(&DebouncedEvent::Rescan));
+    assert!(is_reload_trigger(&DebouncedEvent::Write(PathBuf::from("a.glsl"))));
+  }
+
+  #[test]
+  fn poll_drains_a_burst_of_events_in_one_pass() {
+    let (tx, rx) = channel();
+
+    tx.send(DebouncedEvent::NoticeWrite(PathBuf::from("a.glsl"))).unwrap();
+    tx.send(DebouncedEvent::Write(PathBuf::from("a.glsl"))).unwrap();
+    tx.send(DebouncedEvent::Write(PathBuf::from("b.glsl"))).unwrap();
+
+    // runs the same drain expression `WatchedProgram::poll` uses, on a bare channel, since `poll`
+    // itself needs a live filesystem watcher
+    let changed = rx.try_iter().filter(is_reload_trigger).count() > 0;
+
+    assert!(changed);
+    assert!(rx.try_iter().next().is_none(), "all queued events should be drained in a single poll, not leaked to the next one");
+  }
+}
@@ -0,0 +1,9 @@
+//! Compute shader stage marker.
+
+/// Compute shader.
+///
+/// A compute program is built from a single `Stage<_, ComputeShader>` and cannot be linked
+/// together with the raster stages (vertex, tessellation, geometry, fragment): it runs on its own,
+/// dispatched over a 3D work-group count rather than rasterized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ComputeShader;